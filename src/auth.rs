@@ -0,0 +1,231 @@
+/*!
+Credentials for assets behind authentication
+
+Secrets are only ever read from the environment or `.netrc`, and only end up in
+request headers, never in the manifest, the hash list, errors or verbose output.
+*/
+
+use crate::TaError;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use url::Url;
+
+/// How to authenticate the requests for an asset
+///
+/// In TOML this is a table with a `type`:
+/// ```toml
+/// auth = { type = "bearer", token_env = "GITHUB_TOKEN" }
+/// auth = { type = "basic", user = "ci", password_env = "REGISTRY_PASSWORD" }
+/// auth = { type = "netrc" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Auth {
+    /// `Authorization: Bearer` with the token in the environment variable `token_env`
+    Bearer { token_env: String },
+    /// `Authorization: Basic` with the password in the environment variable `password_env`
+    Basic {
+        user: String,
+        #[serde(default)]
+        password_env: Option<String>,
+    },
+    /// Login and password of the url's host in the `.netrc` file
+    ///
+    /// The file is `$NETRC`, or `.netrc` in the home directory.
+    Netrc,
+}
+
+impl Auth {
+    /// Value of the `Authorization` header for a request to `url`
+    pub(crate) fn authorization(&self, filename: &str, url: &str) -> Result<String, TaError> {
+        match self {
+            Self::Bearer { token_env } => {
+                Ok(format!("Bearer {}", env_secret(filename, token_env)?))
+            }
+            Self::Basic { user, password_env } => {
+                let password = match password_env {
+                    Some(var) => env_secret(filename, var)?,
+                    None => String::new(),
+                };
+                Ok(basic(user, &password))
+            }
+            Self::Netrc => {
+                let host = Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_owned));
+                let login = host.as_deref().and_then(netrc_login);
+                let (user, password) = login.ok_or_else(|| TaError::MissingCredentials {
+                    filename: filename.to_owned(),
+                    what: format!("a .netrc entry for {}", host.as_deref().unwrap_or(url)),
+                })?;
+                Ok(basic(&user, &password))
+            }
+        }
+    }
+}
+
+fn env_secret(filename: &str, var: &str) -> Result<String, TaError> {
+    env::var(var).ok().filter(|v| !v.is_empty()).ok_or_else(|| TaError::MissingCredentials {
+        filename: filename.to_owned(),
+        what: format!("the environment variable {var}"),
+    })
+}
+
+pub(crate) fn basic(user: &str, password: &str) -> String {
+    format!("Basic {}", STANDARD.encode(format!("{user}:{password}")))
+}
+
+/// Replaces every `${VAR}` in a header value with the environment variable `VAR`
+pub(crate) fn expand_env(filename: &str, value: &str) -> Result<String, TaError> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        out.push_str(&env_secret(filename, &rest[start + 2..start + len])?);
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Removes the credentials from `url`, returning them as `Authorization` header value
+///
+/// This keeps them out of errors and the hash list, which only ever see the stripped url.
+pub(crate) fn split_userinfo(url: &str) -> (String, Option<String>) {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return (url.to_owned(), None);
+    };
+    if parsed.username().is_empty() && parsed.password().is_none() {
+        return (url.to_owned(), None);
+    }
+    let decode = |s: &str| percent_encoding::percent_decode_str(s).decode_utf8_lossy().into_owned();
+    let authorization =
+        basic(&decode(parsed.username()), &decode(parsed.password().unwrap_or_default()));
+    let _ = parsed.set_username("");
+    let _ = parsed.set_password(None);
+    (parsed.into(), Some(authorization))
+}
+
+/// Host of a remote `url`
+pub(crate) fn host(url: &str) -> Option<String> {
+    Url::parse(url).ok()?.host_str().map(str::to_ascii_lowercase)
+}
+
+/// Scheme, host and port of a remote `url`, which must all match for asset credentials
+pub(crate) fn origin(url: &str) -> Option<(String, String, u16)> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?.to_ascii_lowercase();
+    Some((url.scheme().to_owned(), host, url.port_or_known_default()?))
+}
+
+fn netrc_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("NETRC").filter(|p| !p.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".netrc"))
+}
+
+/// Login and password for `host` in the `.netrc` file, falling back to its `default` entry
+pub(crate) fn netrc_login(host: &str) -> Option<(String, String)> {
+    let content = fs::read_to_string(netrc_path()?).ok()?;
+    parse_netrc(&content, host)
+}
+
+fn parse_netrc(content: &str, host: &str) -> Option<(String, String)> {
+    let mut found = None;
+    let mut default = None;
+    // (matches host, login, password) of the entry being read
+    let mut current: Option<(bool, Option<String>, Option<String>)> = None;
+    let mut finish = |entry: Option<(bool, Option<String>, Option<String>)>, is_default: bool| {
+        if let Some((matches, login, password)) = entry {
+            let login = (login.unwrap_or_default(), password.unwrap_or_default());
+            if matches && found.is_none() {
+                found = Some(login);
+            } else if is_default && default.is_none() {
+                default = Some(login);
+            }
+        }
+    };
+
+    let mut is_default = false;
+    let mut lines = content.lines();
+    while let Some(line) = lines.next() {
+        let mut tokens = line.split_whitespace();
+        while let Some(token) = tokens.next() {
+            match token {
+                "machine" => {
+                    finish(current.take(), is_default);
+                    let name = tokens.next().unwrap_or_default();
+                    is_default = false;
+                    current = Some((name.eq_ignore_ascii_case(host), None, None));
+                }
+                "default" => {
+                    finish(current.take(), is_default);
+                    is_default = true;
+                    current = Some((false, None, None));
+                }
+                "login" => {
+                    if let Some(entry) = current.as_mut() {
+                        entry.1 = tokens.next().map(str::to_owned);
+                    }
+                }
+                "password" => {
+                    if let Some(entry) = current.as_mut() {
+                        entry.2 = tokens.next().map(str::to_owned);
+                    }
+                }
+                "account" => {
+                    tokens.next();
+                }
+                "macdef" => {
+                    // The macro body runs until the next empty line
+                    for line in lines.by_ref() {
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                    }
+                    break;
+                }
+                _ if token.starts_with('#') => break,
+                _ => {}
+            }
+        }
+    }
+    finish(current, is_default);
+    found.or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NETRC: &str = "\
+machine example.com login alice password s3cret
+machine other.example.com
+    login bob
+    password hunter2
+default login anonymous password guest
+";
+
+    #[test]
+    fn parses_netrc_entries() {
+        let login = |user: &str, password: &str| Some((user.to_owned(), password.to_owned()));
+        assert_eq!(parse_netrc(NETRC, "example.com"), login("alice", "s3cret"));
+        assert_eq!(parse_netrc(NETRC, "EXAMPLE.com"), login("alice", "s3cret"));
+        assert_eq!(parse_netrc(NETRC, "other.example.com"), login("bob", "hunter2"));
+        assert_eq!(parse_netrc(NETRC, "unknown.org"), login("anonymous", "guest"));
+        assert_eq!(parse_netrc("machine example.com login alice", "unknown.org"), None);
+    }
+
+    #[test]
+    fn first_matching_entry_wins() {
+        let netrc = "machine h login first password 1\nmachine h login second password 2\n";
+        assert_eq!(parse_netrc(netrc, "h"), Some(("first".to_owned(), "1".to_owned())));
+    }
+}
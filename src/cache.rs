@@ -0,0 +1,239 @@
+/*!
+Content-addressed cache of assets shared across projects
+*/
+
+use crate::{hash_file, AssetHash};
+use std::env;
+use std::fs::{create_dir_all, hard_link, metadata, read_dir, remove_file, rename, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+/// Suffix of the marker files whose modification time records the last use of an entry
+const USED_SUFFIX: &str = ".used";
+
+/// Distinguishes the temporary files of threads storing the same entry
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A cache of assets keyed by their hash, e.g. `~/.cache/test-assets/sha256/<hex>`
+///
+/// Assets found in the cache are placed into the assets directory without
+/// downloading them, and every downloaded asset is added to the cache.
+///
+/// ```rust, no_run
+/// # use test_assets_ureq::{Cache, Downloader};
+/// let cache = Cache::user_default().unwrap().max_size(10 << 30);
+/// let downloader = Downloader::new("test-assets").cache(cache);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    max_size: Option<u64>,
+    hard_link: bool,
+}
+
+impl Cache {
+    #[must_use]
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into(), max_size: None, hard_link: false }
+    }
+
+    /// The cache in `$XDG_CACHE_HOME/test-assets`, falling back to `~/.cache/test-assets`
+    #[must_use]
+    pub fn user_default() -> Option<Self> {
+        let base = env::var_os("XDG_CACHE_HOME")
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+        Some(Self::new(base.join("test-assets")))
+    }
+
+    /// Evict the least recently used assets once the cache grows beyond `bytes`
+    #[must_use]
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Hard link cached assets into the assets directory instead of copying them
+    ///
+    /// This saves space and time, but a test modifying an asset then modifies the
+    /// cached one as well. Without hard links, copies are reflinked where supported.
+    #[must_use]
+    pub fn hard_link(mut self, hard_link: bool) -> Self {
+        self.hard_link = hard_link;
+        self
+    }
+
+    fn entry_path(&self, hash: &AssetHash) -> PathBuf {
+        self.dir.join(hash.algorithm().name()).join(hash.to_hex())
+    }
+
+    /// Places the cached content for `hash` at `dest`
+    ///
+    /// Returns `false` if the cache doesn't hold it. Entries are verified on the way,
+    /// as they may have been modified in place through a hard link, and corrupt ones
+    /// are evicted.
+    pub(crate) fn fetch(&self, hash: &AssetHash, dest: &str) -> io::Result<bool> {
+        let src = self.entry_path(hash);
+        if !src.is_file() {
+            return Ok(false);
+        }
+        let tmp = format!("{dest}.cached");
+        let _ = remove_file(&tmp);
+        if !(self.hard_link && hard_link(&src, &tmp).is_ok()) {
+            reflink_copy::reflink_or_copy(&src, &tmp)?;
+        }
+        if &hash_file(&tmp, hash.algorithm())? != hash {
+            let _ = remove_file(&tmp);
+            remove_file(&src)?;
+            let _ = remove_file(used_marker(&src));
+            return Ok(false);
+        }
+        rename(&tmp, dest)?;
+        // The asset is in place, a missing marker only makes eviction less accurate
+        let _ = mark_used(&src);
+        Ok(true)
+    }
+
+    /// Adds the file at `src` with content `hash` to the cache
+    pub(crate) fn store(&self, hash: &AssetHash, src: &str) -> io::Result<()> {
+        let dest = self.entry_path(hash);
+        if let Some(parent) = dest.parent() {
+            create_dir_all(parent)?;
+        }
+        if !dest.is_file() {
+            // Other processes may store the same asset concurrently
+            let n = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+            let tmp = dest.with_extension(format!("tmp{}-{n}", process::id()));
+            reflink_copy::reflink_or_copy(src, &tmp)?;
+            rename(&tmp, &dest)?;
+        }
+        mark_used(&dest)?;
+        self.evict()
+    }
+
+    /// Removes the least recently used entries until the cache fits into `max_size`
+    fn evict(&self) -> io::Result<()> {
+        let Some(max_size) = self.max_size else {
+            return Ok(());
+        };
+        let mut entries = Vec::new();
+        for algorithm_dir in read_dir(&self.dir)? {
+            let algorithm_dir = algorithm_dir?.path();
+            if !algorithm_dir.is_dir() {
+                continue;
+            }
+            for entry in read_dir(&algorithm_dir)? {
+                let path = entry?.path();
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                if name.ends_with(USED_SUFFIX) || name.contains(".tmp") {
+                    continue;
+                }
+                let meta = metadata(&path)?;
+                let used = metadata(used_marker(&path))
+                    .and_then(|m| m.modified())
+                    .or_else(|_| meta.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                entries.push((used, meta.len(), path));
+            }
+        }
+
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        entries.sort();
+        for (_, size, path) in entries {
+            if total <= max_size {
+                break;
+            }
+            remove_file(&path)?;
+            let _ = remove_file(used_marker(&path));
+            total -= size;
+        }
+        Ok(())
+    }
+}
+
+fn used_marker(entry: &Path) -> PathBuf {
+    let mut marker = entry.as_os_str().to_owned();
+    marker.push(USED_SUFFIX);
+    PathBuf::from(marker)
+}
+
+/// Records the use of a cache entry for eviction
+///
+/// A separate marker file is touched, as a hard linked entry shares its
+/// modification time with the asset.
+fn mark_used(entry: &Path) -> io::Result<()> {
+    File::create(used_marker(entry)).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{sha256, temp_dir};
+    use std::fs;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Writes `content` to a file in `dir` and returns its path and hash
+    fn source(dir: &str, name: &str, content: &[u8]) -> (String, AssetHash) {
+        let path = format!("{dir}/{name}");
+        fs::write(&path, content).unwrap();
+        (path, AssetHash::parse(&sha256(content)).unwrap())
+    }
+
+    #[test]
+    fn fetches_stored_assets() {
+        let dir = temp_dir("cache-store");
+        let cache = Cache::new(format!("{dir}/cache"));
+        let (src, hash) = source(&dir, "src.bin", b"cached");
+        let dest = format!("{dir}/dest.bin");
+
+        assert!(!cache.fetch(&hash, &dest).unwrap());
+        cache.store(&hash, &src).unwrap();
+        assert!(cache.entry_path(&hash).is_file());
+        assert!(cache.fetch(&hash, &dest).unwrap());
+        assert_eq!(fs::read(&dest).unwrap(), b"cached");
+        assert!(!Path::new(&format!("{dest}.cached")).exists());
+    }
+
+    #[test]
+    fn evicts_corrupt_entries() {
+        let dir = temp_dir("cache-corrupt");
+        let cache = Cache::new(format!("{dir}/cache"));
+        let (src, hash) = source(&dir, "src.bin", b"cached");
+        cache.store(&hash, &src).unwrap();
+        fs::write(cache.entry_path(&hash), b"modified through a hard link").unwrap();
+
+        let dest = format!("{dir}/dest.bin");
+        assert!(!cache.fetch(&hash, &dest).unwrap());
+        assert!(!cache.entry_path(&hash).exists());
+        assert!(!Path::new(&dest).exists());
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entries() {
+        let dir = temp_dir("cache-evict");
+        let cache = Cache::new(format!("{dir}/cache")).max_size(40);
+        let (a_src, a) = source(&dir, "a.bin", b"aaaaaaaaaaaaaaaa");
+        let (b_src, b) = source(&dir, "b.bin", b"bbbbbbbbbbbbbbbb");
+        let (c_src, c) = source(&dir, "c.bin", b"cccccccccccccccc");
+        let pause = || thread::sleep(Duration::from_millis(20));
+
+        cache.store(&a, &a_src).unwrap();
+        pause();
+        cache.store(&b, &b_src).unwrap();
+        pause();
+        // Using `a` makes `b` the least recently used entry
+        assert!(cache.fetch(&a, &format!("{dir}/a-dest.bin")).unwrap());
+        pause();
+        cache.store(&c, &c_src).unwrap();
+
+        assert!(cache.entry_path(&a).is_file());
+        assert!(!cache.entry_path(&b).exists());
+        assert!(cache.entry_path(&c).is_file());
+    }
+}
@@ -0,0 +1,131 @@
+/*!
+Decompression of single-file assets
+*/
+
+use crate::Hasher;
+use flate2::read::MultiGzDecoder;
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Write};
+
+/// Compression of a downloaded file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[serde(alias = "gzip")]
+    Gz,
+    Xz,
+    #[serde(alias = "zstd")]
+    Zst,
+}
+
+/// Writer feeding everything written through it to a hasher
+struct HashingWriter<'a, W: Write> {
+    inner: W,
+    hasher: &'a mut Hasher,
+}
+
+impl<W: Write> Write for HashingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Streams the decompressed content of `src` into `dest`, feeding it to `hasher`
+pub fn decompress_file(
+    src: &str,
+    dest: &str,
+    compression: Compression,
+    hasher: &mut Hasher,
+) -> io::Result<()> {
+    let mut input = BufReader::new(File::open(src)?);
+    let mut output = HashingWriter { inner: BufWriter::new(File::create(dest)?), hasher };
+    let invalid = |e: String| io::Error::new(ErrorKind::InvalidData, e);
+    match compression {
+        Compression::Gz => {
+            io::copy(&mut MultiGzDecoder::new(input), &mut output)?;
+        }
+        Compression::Xz => {
+            lzma_rs::xz_decompress(&mut input, &mut output).map_err(|e| invalid(e.to_string()))?;
+        }
+        Compression::Zst => {
+            let mut decoder =
+                ruzstd::StreamingDecoder::new(input).map_err(|e| invalid(e.to_string()))?;
+            io::copy(&mut decoder, &mut output)?;
+        }
+    }
+    output.flush()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::tests::temp_dir;
+    use crate::HashAlgorithm;
+    use std::fs;
+
+    /// `data` compressed with `compression`
+    ///
+    /// ruzstd can't compress, so zstd gets a frame holding a single raw block.
+    pub(crate) fn compress(compression: Compression, data: &[u8]) -> Vec<u8> {
+        match compression {
+            Compression::Gz => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Xz => {
+                let mut out = Vec::new();
+                lzma_rs::xz_compress(&mut { data }, &mut out).unwrap();
+                out
+            }
+            Compression::Zst => {
+                assert!(data.len() < 256);
+                // Magic number, single segment with a one byte content size, then a last raw block
+                let mut out = vec![0x28, 0xb5, 0x2f, 0xfd, 0x20, data.len() as u8];
+                let block = 1 | ((data.len() as u32) << 3);
+                out.extend_from_slice(&block.to_le_bytes()[..3]);
+                out.extend_from_slice(data);
+                out
+            }
+        }
+    }
+
+    #[test]
+    fn decompresses_every_format() {
+        let dir = temp_dir("decompress");
+        let data = b"0123456789abcdef".repeat(4);
+        for compression in [Compression::Gz, Compression::Xz, Compression::Zst] {
+            let src = format!("{dir}/{compression:?}.src");
+            let dest = format!("{dir}/{compression:?}.dest");
+            fs::write(&src, compress(compression, &data)).unwrap();
+
+            let mut hasher = HashAlgorithm::Sha256.hasher();
+            decompress_file(&src, &dest, compression, &mut hasher).unwrap();
+            assert_eq!(fs::read(&dest).unwrap(), data, "{compression:?}");
+
+            let mut expected = HashAlgorithm::Sha256.hasher();
+            expected.update(&data);
+            assert!(hasher.finalize() == expected.finalize(), "{compression:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_corrupt_streams() {
+        let dir = temp_dir("decompress-file-corrupt");
+        for compression in [Compression::Gz, Compression::Xz, Compression::Zst] {
+            let src = format!("{dir}/{compression:?}.src");
+            fs::write(&src, b"not compressed at all").unwrap();
+            let mut hasher = HashAlgorithm::Sha256.hasher();
+            let dest = format!("{dir}/{compression:?}.dest");
+            assert!(decompress_file(&src, &dest, compression, &mut hasher).is_err());
+        }
+    }
+}
@@ -0,0 +1,292 @@
+/*!
+Hash algorithms and values
+*/
+
+use md5::Md5;
+use sha1::Sha1;
+use sha2::digest::Digest;
+use sha2::{Sha256, Sha512};
+use std::fmt;
+
+/// Algorithms an asset hash can be given in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha512,
+    Blake3,
+    /// Insecure, only for legacy assets without a better published hash
+    Sha1,
+    /// Insecure, only for legacy assets without a better published hash
+    Md5,
+}
+
+impl HashAlgorithm {
+    /// Name used as prefix in `<algorithm>:<hex>` hashes
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+            Self::Blake3 => "blake3",
+            Self::Sha1 => "sha1",
+            Self::Md5 => "md5",
+        }
+    }
+
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "sha256" => Some(Self::Sha256),
+            "sha512" => Some(Self::Sha512),
+            "blake3" => Some(Self::Blake3),
+            "sha1" => Some(Self::Sha1),
+            "md5" => Some(Self::Md5),
+            _ => None,
+        }
+    }
+
+    /// Length of the digest in bytes
+    fn digest_len(self) -> usize {
+        match self {
+            Self::Sha256 | Self::Blake3 => 32,
+            Self::Sha512 => 64,
+            Self::Sha1 => 20,
+            Self::Md5 => 16,
+        }
+    }
+
+    #[must_use]
+    pub fn hasher(self) -> Hasher {
+        Hasher::new(self)
+    }
+}
+
+/// Incremental hasher for any of the supported algorithms
+pub struct Hasher(Inner);
+
+enum Inner {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Blake3(Box<blake3::Hasher>),
+    Sha1(Sha1),
+    Md5(Md5),
+}
+
+impl Hasher {
+    #[must_use]
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        Self(match algorithm {
+            HashAlgorithm::Sha256 => Inner::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => Inner::Sha512(Sha512::new()),
+            HashAlgorithm::Blake3 => Inner::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgorithm::Sha1 => Inner::Sha1(Sha1::new()),
+            HashAlgorithm::Md5 => Inner::Md5(Md5::new()),
+        })
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match &mut self.0 {
+            Inner::Sha256(h) => h.update(data),
+            Inner::Sha512(h) => h.update(data),
+            Inner::Blake3(h) => {
+                h.update(data);
+            }
+            Inner::Sha1(h) => h.update(data),
+            Inner::Md5(h) => h.update(data),
+        }
+    }
+
+    #[must_use]
+    pub fn finalize(self) -> AssetHash {
+        let (algorithm, bytes) = match self.0 {
+            Inner::Sha256(h) => return Sha256Hash::from_digest(h).into(),
+            Inner::Sha512(h) => (HashAlgorithm::Sha512, h.finalize().to_vec()),
+            Inner::Blake3(h) => (HashAlgorithm::Blake3, h.finalize().as_bytes().to_vec()),
+            Inner::Sha1(h) => (HashAlgorithm::Sha1, h.finalize().to_vec()),
+            Inner::Md5(h) => (HashAlgorithm::Md5, h.finalize().to_vec()),
+        };
+        AssetHash { algorithm, bytes }
+    }
+}
+
+/// A hash value together with the algorithm that produced it
+///
+/// Written as `<algorithm>:<hex>`, e.g. `blake3:af13...`. Plain hexadecimal
+/// without prefix is sha256.
+#[derive(PartialEq, Eq, Hash, Clone)]
+pub struct AssetHash {
+    algorithm: HashAlgorithm,
+    bytes: Vec<u8>,
+}
+
+impl AssetHash {
+    /// Parses `<algorithm>:<hex>` or plain sha256 hexadecimal
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        let (algorithm, hex) = match s.split_once(':') {
+            Some((name, hex)) => (HashAlgorithm::from_name(name)?, hex),
+            None => (HashAlgorithm::Sha256, s),
+        };
+        if hex.len() != algorithm.digest_len() * 2 {
+            return None;
+        }
+        let mut bytes = Vec::with_capacity(algorithm.digest_len());
+        let mut iter = hex.chars();
+        while let (Some(upper), Some(lower)) = (iter.next(), iter.next()) {
+            let upper = upper.to_digit(16)? as u8;
+            let lower = lower.to_digit(16)? as u8;
+            bytes.push((upper << 4) | lower);
+        }
+        Some(Self { algorithm, bytes })
+    }
+
+    #[must_use]
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// Converts the hash value to hexadecimal, without algorithm prefix
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        let mut res = String::with_capacity(self.bytes.len() * 2);
+        for v in &self.bytes {
+            use std::char::from_digit;
+            res.push(from_digit(u32::from(*v) >> 4, 16).unwrap());
+            res.push(from_digit(u32::from(*v) & 15, 16).unwrap());
+        }
+        res
+    }
+}
+
+impl fmt::Display for AssetHash {
+    /// Sha256 is written as plain hexadecimal, everything else with prefix
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.algorithm {
+            HashAlgorithm::Sha256 => write!(f, "{}", self.to_hex()),
+            algorithm => write!(f, "{}:{}", algorithm.name(), self.to_hex()),
+        }
+    }
+}
+
+/// A Sha256 hash value, the implementation of [`AssetHash`] for [`HashAlgorithm::Sha256`]
+///
+/// [`Hasher`] finalizes sha256 through this type, which converts into an [`AssetHash`].
+#[derive(PartialEq, Eq, Hash, Clone)]
+pub struct Sha256Hash([u8; 32]);
+
+impl Sha256Hash {
+    #[must_use]
+    pub fn from_digest(sha: Sha256) -> Self {
+        let sha = sha.finalize();
+        let bytes = sha[..].try_into().unwrap();
+        Self(bytes)
+    }
+
+    /// Converts the hash value to hexadecimal
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        AssetHash::from(self.clone()).to_hex()
+    }
+}
+
+impl From<Sha256Hash> for AssetHash {
+    fn from(hash: Sha256Hash) -> Self {
+        Self { algorithm: HashAlgorithm::Sha256, bytes: hash.0.to_vec() }
+    }
+}
+
+impl TryFrom<AssetHash> for Sha256Hash {
+    /// The hash itself, if it is of another algorithm
+    type Error = AssetHash;
+
+    fn try_from(hash: AssetHash) -> Result<Self, AssetHash> {
+        match hash.algorithm {
+            HashAlgorithm::Sha256 => Ok(Self(hash.bytes[..].try_into().unwrap())),
+            _ => Err(hash),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(algorithm: HashAlgorithm, data: &[u8]) -> String {
+        let mut hasher = algorithm.hasher();
+        hasher.update(data);
+        hasher.finalize().to_string()
+    }
+
+    #[test]
+    fn hashes_with_every_algorithm() {
+        let expected = [
+            (
+                HashAlgorithm::Sha256,
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                HashAlgorithm::Sha512,
+                "sha512:ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+                 2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            ),
+            (
+                HashAlgorithm::Blake3,
+                "blake3:6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            ),
+            (HashAlgorithm::Sha1, "sha1:a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (HashAlgorithm::Md5, "md5:900150983cd24fb0d6963f7d28e17f72"),
+        ];
+        for (algorithm, hex) in expected {
+            assert_eq!(hash(algorithm, b"abc"), hex);
+            let parsed = AssetHash::parse(hex).unwrap();
+            assert_eq!(parsed.algorithm(), algorithm);
+            assert_eq!(parsed.to_string(), hex);
+        }
+    }
+
+    #[test]
+    fn converts_sha256_hashes() {
+        let mut hasher = HashAlgorithm::Sha256.hasher();
+        hasher.update(b"abc");
+        let hash = hasher.finalize();
+        let Ok(sha256) = Sha256Hash::try_from(hash.clone()) else { panic!("{hash}") };
+        assert_eq!(sha256.to_hex(), hash.to_hex());
+        assert!(AssetHash::from(sha256) == hash);
+
+        let md5 = AssetHash::parse("md5:900150983cd24fb0d6963f7d28e17f72").unwrap();
+        assert!(Sha256Hash::try_from(md5.clone()).err() == Some(md5));
+    }
+
+    #[test]
+    fn parses_prefixes_case_insensitively() {
+        let hex = "a9993e364706816aba3e25717850c26c9cd0d89d";
+        for prefix in ["sha1", "SHA1", "Sha1"] {
+            let parsed = AssetHash::parse(&format!("{prefix}:{hex}")).unwrap();
+            assert_eq!(parsed.algorithm(), HashAlgorithm::Sha1);
+        }
+        assert!(AssetHash::parse(&format!("sha3:{hex}")).is_none());
+    }
+
+    #[test]
+    fn rejects_hex_of_the_wrong_length() {
+        let algorithms = [
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Sha512,
+            HashAlgorithm::Blake3,
+            HashAlgorithm::Sha1,
+            HashAlgorithm::Md5,
+        ];
+        for algorithm in algorithms {
+            let hex = hash(algorithm, b"abc");
+            let hex = hex.rsplit(':').next().unwrap();
+            let name = algorithm.name();
+            assert!(AssetHash::parse(&format!("{name}:{hex}")).is_some(), "{name}");
+            assert!(AssetHash::parse(&format!("{name}:{hex}00")).is_none(), "{name}");
+            assert!(AssetHash::parse(&format!("{name}:{}", &hex[2..])).is_none(), "{name}");
+        }
+        // Only sha256 may be given without prefix
+        assert!(AssetHash::parse("900150983cd24fb0d6963f7d28e17f72").is_none());
+        assert!(AssetHash::parse("md5:900150983cd24fb0d6963f7d28e17fzz").is_none());
+    }
+}
@@ -0,0 +1,84 @@
+/*!
+Where downloaded assets ended up
+*/
+
+use std::collections::btree_map;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// How an asset came to be present
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetStatus {
+    /// It already was, and still matches the hash list
+    Verified,
+    /// It was copied from the [`Cache`](crate::Cache)
+    Cached,
+    /// It was downloaded from its url or a mirror
+    Downloaded,
+}
+
+/// An asset present in the assets directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadedAsset {
+    /// Absolute path of the file
+    pub path: PathBuf,
+    /// Absolute path of the directory the archive was unpacked into, for assets with `extract`
+    pub extracted: Option<PathBuf>,
+    pub status: AssetStatus,
+}
+
+/// Assets of a download, by their key
+///
+/// The key is the one of the [`TestAsset`](crate::TestAsset) manifest for
+/// [`Downloader::download_assets`](crate::Downloader::download_assets), and the filename otherwise.
+///
+/// ```rust, no_run
+/// # use test_assets_ureq::{dl_test_files, TestAssetDef};
+/// # let defs: Vec<TestAssetDef> = vec![];
+/// let assets = dl_test_files(&defs, "test-assets", true).unwrap();
+/// let image = std::fs::read(assets.path("image.squashfs").unwrap()).unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DownloadedAssets {
+    assets: BTreeMap<String, DownloadedAsset>,
+}
+
+impl DownloadedAssets {
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&DownloadedAsset> {
+        self.assets.get(key)
+    }
+
+    /// Absolute path of the asset `key`
+    #[must_use]
+    pub fn path(&self, key: &str) -> Option<&Path> {
+        self.get(key).map(|asset| asset.path.as_path())
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<'_, String, DownloadedAsset> {
+        self.assets.iter()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    pub(crate) fn insert(&mut self, key: &str, asset: DownloadedAsset) {
+        self.assets.insert(key.to_owned(), asset);
+    }
+}
+
+impl<'a> IntoIterator for &'a DownloadedAssets {
+    type Item = (&'a String, &'a DownloadedAsset);
+    type IntoIter = btree_map::Iter<'a, String, DownloadedAsset>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,151 @@
+/*!
+Error type
+*/
+
+use crate::{FetchError, ManifestProblem};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum TaError {
+    /// I/O failure not tied to a single asset, e.g. while handling the hash list
+    Io(io::Error),
+    /// I/O failure while downloading or storing an asset
+    AssetIo { filename: String, source: io::Error },
+    /// The request failed before the server answered (DNS, connection, TLS, ...)
+    Transport { filename: String, url: String, source: FetchError },
+    /// The server answered with an error status
+    Status { filename: String, url: String, status: u16, retry_after: Option<Duration> },
+    /// The server answered a `Range` request with content from another position
+    BadContentRange { filename: String, url: String },
+    /// The downloaded content doesn't match the expected hash
+    HashMismatch { filename: String, expected: String, found: String },
+    /// The hash is not valid hexadecimal of the expected length, or names an unknown algorithm
+    BadHashFormat { filename: String, hash: String },
+    /// The filename could place the asset outside of the assets directory
+    InvalidFilename { filename: String, reason: &'static str },
+    /// The asset should be extracted, but its filename has no known archive extension
+    UnsupportedArchive { filename: String },
+    /// The directory an archive is extracted into holds `asset`, which extracting would delete
+    ExtractConflict { filename: String, extract: String, asset: String },
+    /// The directory an archive is extracted into is, contains or is inside the one `other` is
+    /// extracted into
+    ExtractOverlap { filename: String, extract: String, other: String },
+    /// The body ended before the announced `Content-Length`
+    TruncatedBody { filename: String, url: String, expected: u64, actual: u64 },
+    /// The asset is missing or modified, but offline mode forbids downloading it
+    Offline { filename: String },
+    /// An https url redirected to `location` over plain http, which would expose the request
+    InsecureRedirect { filename: String, url: String, location: String },
+    /// Credentials configured for the asset are not available, `what` names their source
+    MissingCredentials { filename: String, what: String },
+    /// The asset manifest can't be read or parsed, or defines invalid assets
+    ///
+    /// `path` is missing for manifests not read from a file.
+    InvalidManifest { path: Option<String>, problems: Vec<ManifestProblem> },
+    /// Neither the url nor any mirror of an asset delivered it
+    AllMirrorsFailed { filename: String, errors: Vec<TaError> },
+    /// More than one asset failed to download
+    Multiple(Vec<TaError>),
+}
+
+impl From<io::Error> for TaError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl TaError {
+    /// Attributes a plain I/O error to the asset `filename`
+    pub(crate) fn for_asset(self, filename: &str) -> Self {
+        match self {
+            Self::Io(source) => Self::AssetIo { filename: filename.to_owned(), source },
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for TaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            Self::AssetIo { filename, source } => write!(f, "{filename}: I/O error: {source}"),
+            Self::Transport { filename, url, source } => {
+                write!(f, "{filename}: request to {url} failed: {source}")
+            }
+            Self::Status { filename, url, status, .. } => {
+                write!(f, "{filename}: {url} answered with HTTP status {status}")
+            }
+            Self::BadContentRange { filename, url } => {
+                write!(f, "{filename}: {url} answered with an unexpected Content-Range")
+            }
+            Self::HashMismatch { filename, expected, found } => {
+                write!(f, "{filename}: hash mismatch, expected {expected}, found {found}")
+            }
+            Self::BadHashFormat { filename, hash } => {
+                write!(f, "{filename}: `{hash}` is not a valid hash")
+            }
+            Self::InvalidFilename { filename, reason } => {
+                write!(f, "{filename}: invalid filename, {reason}")
+            }
+            Self::ExtractConflict { filename, extract, asset } => {
+                write!(f, "{filename}: can't extract into {extract}, it holds the asset {asset}")
+            }
+            Self::ExtractOverlap { filename, extract, other } => {
+                let why = format!("{other} is extracted into the same or a nested directory");
+                write!(f, "{filename}: can't extract into {extract}, {why}")
+            }
+            Self::UnsupportedArchive { filename } => {
+                write!(f, "{filename}: can't extract, unknown archive format")
+            }
+            Self::TruncatedBody { filename, url, expected, actual } => {
+                write!(f, "{filename}: body from {url} ended after {actual} of {expected} bytes")
+            }
+            Self::Offline { filename } => {
+                write!(f, "{filename}: missing or modified, and not downloaded in offline mode")
+            }
+            Self::InsecureRedirect { filename, url, location } => {
+                write!(f, "{filename}: {url} redirected to {location}, refusing to leave https")
+            }
+            Self::MissingCredentials { filename, what } => {
+                write!(f, "{filename}: missing credentials, {what} is not set")
+            }
+            Self::InvalidManifest { path, problems } => {
+                match path {
+                    Some(path) => write!(f, "{path}: invalid manifest:")?,
+                    None => write!(f, "invalid manifest:")?,
+                }
+                for problem in problems {
+                    write!(f, "\n  {problem}")?;
+                }
+                Ok(())
+            }
+            Self::AllMirrorsFailed { filename, errors } => {
+                write!(f, "{filename}: all {} urls failed:", errors.len())?;
+                for e in errors {
+                    write!(f, "\n    {e}")?;
+                }
+                Ok(())
+            }
+            Self::Multiple(errors) => {
+                write!(f, "{} assets failed:", errors.len())?;
+                for e in errors {
+                    write!(f, "\n  {e}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for TaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) | Self::AssetIo { source: e, .. } => Some(e),
+            Self::Transport { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,204 @@
+/*!
+Extraction of archive assets
+*/
+
+use flate2::read::GzDecoder;
+use std::fs::{create_dir_all, remove_dir_all, remove_file, rename, File};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Component, Path};
+
+/// Archive formats that can be extracted, detected from the filename
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    TarXz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let name = filename.to_ascii_lowercase();
+        if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+            Some(Self::TarXz)
+        } else if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// Extracts `archive` into the directory `dest`, replacing its previous content
+///
+/// The archive is unpacked into a temporary directory first, so `dest` never holds
+/// a partial extraction. Entries that would end up outside of `dest` fail the extraction.
+/// Callers make sure `dest` contains no assets, as all of it is replaced.
+pub fn extract_archive(archive: &str, format: ArchiveFormat, dest: &str) -> io::Result<()> {
+    let tmp = Path::new(dest).with_file_name(format!(
+        "{}.extracting",
+        Path::new(dest).file_name().unwrap_or_default().to_string_lossy()
+    ));
+    if tmp.exists() {
+        remove_dir_all(&tmp)?;
+    }
+    let content = tmp.join("content");
+    create_dir_all(&content)?;
+
+    let result = unpack(archive, format, &tmp, &content).and_then(|()| {
+        if Path::new(dest).exists() {
+            remove_dir_all(dest)?;
+        }
+        rename(&content, dest)
+    });
+    let _ = remove_dir_all(&tmp);
+    result
+}
+
+/// Unpacks `archive` into `dest`, using `tmp` for intermediate files
+fn unpack(archive: &str, format: ArchiveFormat, tmp: &Path, dest: &Path) -> io::Result<()> {
+    let file = BufReader::new(File::open(archive)?);
+    match format {
+        ArchiveFormat::Tar => unpack_tar(file, dest),
+        ArchiveFormat::TarGz => unpack_tar(GzDecoder::new(file), dest),
+        ArchiveFormat::TarXz => {
+            // lzma-rs only decodes into a writer, so go through an intermediate tar
+            let tar_path = tmp.join("archive.tar");
+            let result = (|| -> io::Result<()> {
+                let mut writer = BufWriter::new(File::create(&tar_path)?);
+                lzma_rs::xz_decompress(&mut { file }, &mut writer)
+                    .map_err(|e| io::Error::new(ErrorKind::InvalidData, e.to_string()))?;
+                writer.flush()?;
+                unpack_tar(BufReader::new(File::open(&tar_path)?), dest)
+            })();
+            let _ = remove_file(&tar_path);
+            result
+        }
+        ArchiveFormat::Zip => unpack_zip(file, dest),
+    }
+}
+
+fn unpack_tar<R: Read>(reader: R, dest: &Path) -> io::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if !is_enclosed(&path) {
+            return Err(unsafe_path(&path));
+        }
+        // `unpack_in` additionally refuses to write through symlinks leading out of `dest`
+        entry.unpack_in(dest)?;
+    }
+    Ok(())
+}
+
+fn unpack_zip(reader: BufReader<File>, dest: &Path) -> io::Result<()> {
+    let zip_err = |e: zip::result::ZipError| io::Error::new(ErrorKind::InvalidData, e.to_string());
+    let mut archive = zip::ZipArchive::new(reader).map_err(zip_err)?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(zip_err)?;
+        let Some(name) = file.enclosed_name() else {
+            return Err(unsafe_path(Path::new(file.name())));
+        };
+        let out = dest.join(name);
+        if file.is_dir() {
+            create_dir_all(&out)?;
+            continue;
+        }
+        if let Some(parent) = out.parent() {
+            create_dir_all(parent)?;
+        }
+        io::copy(&mut file, &mut BufWriter::new(File::create(&out)?))?;
+    }
+    Ok(())
+}
+
+/// Whether `path` stays below the directory it is relative to
+fn is_enclosed(path: &Path) -> bool {
+    path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+fn unsafe_path(path: &Path) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("archive entry {} would be extracted outside of the destination", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::temp_dir;
+    use std::fs;
+
+    /// A tar holding `entries`, written without the path checks of `tar::Builder`
+    fn write_tar(path: &str, entries: &[(&str, &[u8])]) {
+        let mut builder = tar::Builder::new(File::create(path).unwrap());
+        for (name, data) in entries {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, *data).unwrap();
+        }
+        builder.finish().unwrap();
+    }
+
+    fn write_zip(path: &str, entries: &[(&str, &[u8])]) {
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        for (name, data) in entries {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn replaces_previous_content() {
+        let dir = temp_dir("extract-tar");
+        let archive = format!("{dir}/data.tar");
+        write_tar(&archive, &[("a.txt", b"a"), ("sub/b.txt", b"b")]);
+        let dest = format!("{dir}/data");
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(format!("{dest}/stale.txt"), b"stale").unwrap();
+
+        extract_archive(&archive, ArchiveFormat::Tar, &dest).unwrap();
+        assert_eq!(fs::read(format!("{dest}/a.txt")).unwrap(), b"a");
+        assert_eq!(fs::read(format!("{dest}/sub/b.txt")).unwrap(), b"b");
+        assert!(!Path::new(&dest).join("stale.txt").exists());
+        assert!(!Path::new(&format!("{dir}/data.extracting")).exists());
+    }
+
+    #[test]
+    fn rejects_tar_slip() {
+        let dir = temp_dir("extract-tar-slip");
+        let archive = format!("{dir}/evil.tar");
+        write_tar(&archive, &[("ok.txt", b"ok"), ("../evil.txt", b"evil")]);
+        let dest = format!("{dir}/data");
+
+        let err = extract_archive(&archive, ArchiveFormat::Tar, &dest).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(!Path::new(&dir).join("evil.txt").exists());
+        assert!(!Path::new(&dest).exists());
+    }
+
+    #[test]
+    fn rejects_zip_slip() {
+        let dir = temp_dir("extract-zip-slip");
+        let archive = format!("{dir}/evil.zip");
+        write_zip(&archive, &[("ok.txt", b"ok"), ("../evil.txt", b"evil")]);
+        let dest = format!("{dir}/data");
+
+        let err = extract_archive(&archive, ArchiveFormat::Zip, &dest).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(!Path::new(&dir).join("evil.txt").exists());
+        assert!(!Path::new(&dest).exists());
+    }
+}
@@ -8,16 +8,64 @@
 Hash list module
 */
 
-use crate::Sha256Hash;
+use crate::AssetHash;
 use crate::TaError;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::process;
+use std::time::UNIX_EPOCH;
 use std::{
-    fs::File,
-    io::{BufRead, BufReader, BufWriter, Write},
+    fs::{metadata, remove_file, rename, File},
+    io::{self, BufRead, BufReader, BufWriter, ErrorKind, Write},
 };
 
+/// Size and modification time of a file, used to cheaply detect changes to it
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct FileStamp {
+    pub size: u64,
+    /// Nanoseconds since the unix epoch
+    pub mtime: u64,
+}
+
+impl FileStamp {
+    pub fn from_path(path: &str) -> io::Result<Self> {
+        let meta = metadata(path)?;
+        let mtime = meta.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default();
+        Ok(Self { size: meta.len(), mtime: mtime.as_nanos() as u64 })
+    }
+}
+
+#[derive(Clone)]
+pub struct HashListEntry {
+    pub hash: AssetHash,
+    /// Stamp of the file when it was verified, missing in entries from older versions
+    pub stamp: Option<FileStamp>,
+    /// The url the file was downloaded from
+    pub url: Option<String>,
+    /// `ETag` the server sent along with the file
+    pub etag: Option<String>,
+    /// Seconds since the unix epoch when the file was downloaded
+    pub downloaded: Option<u64>,
+    /// Hash of the file on disk, if it isn't `hash` because the download was decompressed
+    pub file_hash: Option<AssetHash>,
+}
+
+impl HashListEntry {
+    pub fn new(hash: AssetHash) -> Self {
+        Self { hash, stamp: None, url: None, etag: None, downloaded: None, file_hash: None }
+    }
+}
+
+/// Current version of the on-disk format
+///
+/// Version 2 files start with a `# hash_list v2` line, followed by lines
+/// `<hash> <name> <size> <mtime> <downloaded> <url> <etag> <file_hash>` with escaped
+/// string fields and `-` for missing values. Hashes are `<algorithm>:<hex>`, or plain
+/// hex for sha256. Unversioned files are read as version 1, with `<hash> <name>` lines.
+const VERSION: u32 = 2;
+const VERSION_PREFIX: &str = "# hash_list v";
+
 pub struct HashList {
-    name_to_hash_map: HashMap<String, Sha256Hash>,
+    name_to_hash_map: BTreeMap<String, HashListEntry>,
 }
 
 impl HashList {
@@ -28,49 +76,212 @@ impl HashList {
     }
 
     pub fn from_reader<T: BufRead>(brdr: &mut T) -> Result<Self, TaError> {
-        let mut name_to_hash_map = HashMap::new();
+        let mut name_to_hash_map = BTreeMap::new();
+        let mut version = 1;
         for oline in brdr.lines() {
             let line = oline?;
-            if line.starts_with('#') {
+            if let Some(v) = line.strip_prefix(VERSION_PREFIX) {
+                version = v.trim().parse().unwrap_or(u32::MAX);
+                if version > VERSION {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("unsupported hash list version {}", v.trim()),
+                    )
+                    .into());
+                }
                 continue;
             }
-            let mut spi = line.split(' ');
-            let hash_str = match spi.next() {
-                Some(v) => v,
-                None => continue,
+            if line.starts_with('#') || line.is_empty() {
+                continue;
+            }
+            let Some((hash_str, rest)) = line.split_once(' ') else {
+                continue;
             };
-            let hash = Sha256Hash::from_hex(hash_str).map_err(|_| TaError::BadHashFormat)?;
+            // Version 1 names are unescaped, so may contain spaces up to the end of the line
+            let fields: Vec<&str> =
+                if version >= 2 { rest.split(' ').collect() } else { vec![rest] };
+            let mut spi = fields.into_iter();
             let name = match spi.next() {
-                Some(v) => v,
+                Some(v) if version >= 2 => unescape(v),
+                Some(v) => v.to_owned(),
                 None => continue,
             };
-            name_to_hash_map.insert(name.to_owned(), hash);
+            let hash = AssetHash::parse(hash_str).ok_or_else(|| TaError::BadHashFormat {
+                filename: name.clone(),
+                hash: hash_str.to_owned(),
+            })?;
+            let size = spi.next().and_then(|v| v.parse().ok());
+            let mtime = spi.next().and_then(|v| v.parse().ok());
+            let stamp = match (size, mtime) {
+                (Some(size), Some(mtime)) => Some(FileStamp { size, mtime }),
+                _ => None,
+            };
+            let downloaded = spi.next().and_then(|v| v.parse().ok());
+            let url = spi.next().and_then(optional_field);
+            let etag = spi.next().and_then(optional_field);
+            let file_hash = spi.next().and_then(AssetHash::parse);
+            name_to_hash_map
+                .insert(name, HashListEntry { hash, stamp, url, etag, downloaded, file_hash });
         }
         Ok(Self { name_to_hash_map })
     }
 
+    /// Writes the list to a temporary file renamed to `path`, so readers never see it half written
     pub fn to_file(&self, path: &str) -> Result<(), TaError> {
-        let wrt = File::create(path)?;
-        let mut bwrtr = BufWriter::new(wrt);
-        self.to_writer(&mut bwrtr)
+        let tmp_path = format!("{path}.tmp{}", process::id());
+        let result = File::create(&tmp_path).map_err(TaError::from).and_then(|wrt| {
+            let mut bwrtr = BufWriter::new(wrt);
+            self.to_writer(&mut bwrtr)
+        });
+        match result.and_then(|()| Ok(rename(&tmp_path, path)?)) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                let _ = remove_file(&tmp_path);
+                Err(e)
+            }
+        }
     }
 
     pub fn to_writer<W: Write>(&self, bwrtr: &mut BufWriter<W>) -> Result<(), TaError> {
-        for (name, hash) in &self.name_to_hash_map {
-            bwrtr.write_all(format!("{} {}\n", hash.to_hex(), name).as_bytes())?;
+        writeln!(bwrtr, "{VERSION_PREFIX}{VERSION}")?;
+        for (name, entry) in &self.name_to_hash_map {
+            let (size, mtime) = match entry.stamp {
+                Some(stamp) => (stamp.size.to_string(), stamp.mtime.to_string()),
+                None => ("-".to_owned(), "-".to_owned()),
+            };
+            writeln!(
+                bwrtr,
+                "{} {} {} {} {} {} {} {}",
+                entry.hash,
+                escape(name),
+                size,
+                mtime,
+                entry.downloaded.map_or_else(|| "-".to_owned(), |v| v.to_string()),
+                entry.url.as_deref().map_or_else(|| "-".to_owned(), escape),
+                entry.etag.as_deref().map_or_else(|| "-".to_owned(), escape),
+                entry.file_hash.as_ref().map_or_else(|| "-".to_owned(), AssetHash::to_string),
+            )?;
         }
+        bwrtr.flush()?;
         Ok(())
     }
 
     pub fn new() -> Self {
-        Self { name_to_hash_map: HashMap::new() }
+        Self { name_to_hash_map: BTreeMap::new() }
     }
 
-    pub fn get_hash<'a>(&'a self, filename: &str) -> Option<&'a Sha256Hash> {
+    pub fn get(&self, filename: &str) -> Option<&HashListEntry> {
         self.name_to_hash_map.get(filename)
     }
 
-    pub fn add_entry(&mut self, filename: &str, hash: &Sha256Hash) {
-        self.name_to_hash_map.insert(filename.to_owned(), hash.clone());
+    pub fn add_entry(&mut self, filename: &str, entry: HashListEntry) {
+        self.name_to_hash_map.insert(filename.to_owned(), entry);
+    }
+}
+
+/// Escapes a string field, so that it contains neither spaces nor line breaks
+///
+/// A lone `-` is escaped too, as it marks missing values.
+fn escape(s: &str) -> String {
+    if s == "-" {
+        return "\\-".to_owned();
+    }
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            ' ' => res.push_str("\\s"),
+            '\t' => res.push_str("\\t"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+fn unescape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => res.push(' '),
+            Some('t') => res.push('\t'),
+            Some('n') => res.push('\n'),
+            Some('r') => res.push('\r'),
+            Some(c) => res.push(c),
+            None => res.push('\\'),
+        }
+    }
+    res
+}
+
+fn optional_field(s: &str) -> Option<String> {
+    (s != "-").then(|| unescape(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const SHA256: &str = "976c1638d8c1ba8014de6c64b196cbd70a5acf031be10a8e7f649536193c8e78";
+
+    fn round_trip(list: &HashList) -> HashList {
+        let mut bwrtr = BufWriter::new(Vec::new());
+        list.to_writer(&mut bwrtr).unwrap();
+        let written = bwrtr.into_inner().unwrap();
+        HashList::from_reader(&mut Cursor::new(written)).unwrap()
+    }
+
+    #[test]
+    fn reads_version_1_names_with_spaces() {
+        let v1 = format!("{SHA256} out.squashfs\n{SHA256} name with  spaces.bin\n");
+        let list = HashList::from_reader(&mut Cursor::new(v1)).unwrap();
+        assert!(list.get("out.squashfs").is_some());
+        assert!(list.get("name with  spaces.bin").is_some());
+
+        let list = round_trip(&list);
+        let entry = list.get("name with  spaces.bin").unwrap();
+        assert_eq!(entry.hash.to_string(), SHA256);
+        assert!(entry.stamp.is_none() && entry.url.is_none() && entry.etag.is_none());
+    }
+
+    #[test]
+    fn round_trips_escaped_fields() {
+        let mut list = HashList::new();
+        let names = ["a b\tc\\d", "-", "line\nbreak", "fw/v1/image.bin", "data/"];
+        for name in names {
+            let entry = HashListEntry {
+                stamp: Some(FileStamp { size: 16, mtime: 1_700_000_000_000_000_000 }),
+                url: Some("https://example.com/a b".to_owned()),
+                etag: Some("\"v 1\"".to_owned()),
+                downloaded: Some(1_700_000_000),
+                file_hash: AssetHash::parse(&format!("blake3:{SHA256}")),
+                ..HashListEntry::new(AssetHash::parse(SHA256).unwrap())
+            };
+            list.add_entry(name, entry);
+        }
+
+        let list = round_trip(&list);
+        for name in names {
+            let entry = list.get(name).unwrap();
+            assert_eq!(entry.hash.to_string(), SHA256);
+            assert!(entry.stamp == Some(FileStamp { size: 16, mtime: 1_700_000_000_000_000_000 }));
+            assert_eq!(entry.url.as_deref(), Some("https://example.com/a b"));
+            assert_eq!(entry.etag.as_deref(), Some("\"v 1\""));
+            assert_eq!(entry.downloaded, Some(1_700_000_000));
+            assert_eq!(entry.file_hash.as_ref().unwrap().to_string(), format!("blake3:{SHA256}"));
+        }
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let newer = format!("{VERSION_PREFIX}{}\n{SHA256} a.bin\n", VERSION + 1);
+        assert!(HashList::from_reader(&mut Cursor::new(newer)).is_err());
     }
 }
@@ -0,0 +1,26 @@
+/*!
+Advisory file locks shared with other processes using the same assets directory
+*/
+
+use fs4::FileExt;
+use std::fs::{File, OpenOptions};
+use std::io;
+
+/// Exclusive lock on a lockfile, released when dropped
+///
+/// Lockfiles are left behind, as removing them would race with processes about to lock them.
+pub(crate) struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Blocks until the lock on `path` is acquired, calling `waiting` first if it is held elsewhere
+    pub(crate) fn exclusive(path: &str, waiting: impl FnOnce()) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).truncate(false).write(true).open(path)?;
+        if file.try_lock_exclusive().is_err() {
+            waiting();
+            file.lock_exclusive()?;
+        }
+        Ok(Self { _file: file })
+    }
+}
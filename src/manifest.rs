@@ -0,0 +1,654 @@
+/*!
+Loading and validation of asset manifests
+*/
+
+use crate::extract::ArchiveFormat;
+use crate::paths::{absolute, check_relative, is_within, normalize};
+use crate::{AssetHash, TaError, TestAsset, TestAssetDef};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use toml::Spanned;
+
+/// Something wrong with a manifest, located as precisely as possible
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestProblem {
+    /// Key of the asset in [`TestAsset::assets`]
+    pub key: Option<String>,
+    /// Line in the manifest, starting at 1
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ManifestProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {line}: ")?;
+        }
+        if let Some(ref key) = self.key {
+            write!(f, "{key}: ")?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+type SpannedAssets = BTreeMap<String, Spanned<TestAssetDef>>;
+
+/// [`TestAsset`] keeping where each asset is defined
+#[derive(Deserialize)]
+struct SpannedManifest {
+    test_assets: SpannedAssets,
+}
+
+/// The parts of a `Cargo.toml` that can hold assets
+#[derive(Deserialize)]
+struct CargoManifest {
+    package: Option<CargoPackage>,
+    workspace: Option<CargoWorkspace>,
+}
+
+#[derive(Deserialize)]
+struct CargoPackage {
+    /// Path to the workspace root, if it isn't the closest parent with a `[workspace]`
+    workspace: Option<String>,
+    #[serde(default)]
+    metadata: CargoMetadata,
+}
+
+#[derive(Deserialize)]
+struct CargoWorkspace {
+    /// Globs of the member directories, relative to the root
+    #[serde(default)]
+    members: Vec<String>,
+    /// Directories below the root that aren't members
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    metadata: CargoMetadata,
+}
+
+impl CargoWorkspace {
+    /// Whether the package in `dir` belongs to the workspace rooted at `root`
+    fn has_member(&self, root: &Path, dir: &Path) -> bool {
+        let Ok(relative) = dir.strip_prefix(root) else {
+            return false;
+        };
+        let excluded = self.exclude.iter().any(|excluded| relative.starts_with(excluded));
+        !excluded && self.members.iter().any(|member| matches_glob(member, relative))
+    }
+}
+
+/// Whether `path` matches `pattern`, whose components may contain `*` and `?` wildcards
+fn matches_glob(pattern: &str, path: &Path) -> bool {
+    let pattern: Vec<_> = Path::new(pattern).components().map(|c| c.as_os_str()).collect();
+    let path: Vec<_> = path.components().map(|c| c.as_os_str()).collect();
+    pattern.len() == path.len()
+        && pattern.iter().zip(&path).all(|(pattern, name)| {
+            matches_wildcards(pattern.as_encoded_bytes(), name.as_encoded_bytes())
+        })
+}
+
+fn matches_wildcards(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, _) => name.is_empty(),
+        (Some((b'*', rest)), _) => {
+            matches_wildcards(rest, name)
+                || !name.is_empty() && matches_wildcards(pattern, &name[1..])
+        }
+        (Some((b'?', rest)), Some((_, name_rest))) => matches_wildcards(rest, name_rest),
+        (Some((p, rest)), Some((n, name_rest))) => p == n && matches_wildcards(rest, name_rest),
+        (Some(_), None) => false,
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct CargoMetadata {
+    #[serde(default, rename = "test-assets")]
+    test_assets: SpannedAssets,
+}
+
+/// Assets with the line they are defined at
+type LocatedAssets = BTreeMap<String, (Option<usize>, TestAssetDef)>;
+
+fn invalid(path: Option<&Path>, problems: Vec<ManifestProblem>) -> TaError {
+    TaError::InvalidManifest { path: path.map(|p| p.display().to_string()), problems }
+}
+
+fn read(path: &Path) -> Result<String, TaError> {
+    fs::read_to_string(path).map_err(|e| {
+        invalid(Some(path), vec![ManifestProblem { key: None, line: None, message: e.to_string() }])
+    })
+}
+
+/// Line of the byte `offset` in `s`, starting at 1
+fn line(s: &str, offset: usize) -> usize {
+    s.bytes().take(offset).filter(|&b| b == b'\n').count() + 1
+}
+
+fn parse_toml<T: DeserializeOwned>(s: &str) -> Result<T, Vec<ManifestProblem>> {
+    toml::from_str(s).map_err(|e| {
+        vec![ManifestProblem {
+            key: None,
+            line: e.span().map(|span| line(s, span.start)),
+            message: e.message().to_owned(),
+        }]
+    })
+}
+
+/// `url` with a relative filesystem path resolved from `dir`, the directory of the manifest
+fn resolve_source(url: &str, dir: &Path) -> String {
+    if url.contains("://") || Path::new(url).is_absolute() {
+        return url.to_owned();
+    }
+    dir.join(url).to_string_lossy().into_owned()
+}
+
+/// Whether the filesystem path `url` starts with something like a host, e.g. `example.com/a.bin`
+fn looks_like_host(url: &str) -> bool {
+    let Some((first, _)) = url.split_once('/') else {
+        return false;
+    };
+    let Some((name, tld)) = first.rsplit_once('.') else {
+        return false;
+    };
+    !name.is_empty()
+        && !name.starts_with('.')
+        && tld.len() >= 2
+        && tld.bytes().all(|b| b.is_ascii_alphabetic())
+}
+
+/// Checks every asset on its own, `s` being the TOML they were parsed from and `dir`
+/// the directory relative sources are resolved from
+fn validate(
+    s: &str,
+    assets: SpannedAssets,
+    dir: &Path,
+    problems: &mut Vec<ManifestProblem>,
+) -> LocatedAssets {
+    for (key, def) in &assets {
+        let mut problem = |message: String| {
+            problems.push(ManifestProblem {
+                key: Some(key.clone()),
+                line: Some(line(s, def.span().start)),
+                message,
+            });
+        };
+        let tfile = def.get_ref();
+        if AssetHash::parse(&tfile.hash).is_none() {
+            problem(format!("`{}` is not a valid hash", tfile.hash));
+        }
+        if let Err(reason) = check_relative(&tfile.filename) {
+            problem(format!("invalid filename `{}`, {reason}", tfile.filename));
+        }
+        if tfile.urls().any(|url| url.trim().is_empty()) {
+            problem("empty url".to_owned());
+        }
+        // A missing `https://` would otherwise only show up as a missing local file
+        let typo = tfile.urls().find(|url| {
+            !url.contains("://")
+                && looks_like_host(url)
+                && !Path::new(&resolve_source(url, dir)).exists()
+        });
+        if let Some(url) = typo {
+            problem(format!(
+                "`{url}` has no scheme, write `https://{url}` for a remote file \
+                 or `./{url}` for a local one"
+            ));
+        }
+        if tfile.hash_decompressed && tfile.decompress.is_none() {
+            problem("`hash_decompressed` is set, but not `decompress`".to_owned());
+        }
+        if let Some(ref subdir) = tfile.extract {
+            if let Err(reason) = check_relative(subdir) {
+                problem(format!("invalid extract directory `{subdir}`, {reason}"));
+            }
+            if ArchiveFormat::from_filename(&tfile.filename).is_none() {
+                problem("can't extract, unknown archive format".to_owned());
+            }
+        }
+    }
+    assets
+        .into_iter()
+        .map(|(key, def)| {
+            let line = Some(line(s, def.span().start));
+            let mut tfile = def.into_inner();
+            tfile.url = resolve_source(&tfile.url, dir);
+            for mirror in &mut tfile.mirrors {
+                *mirror = resolve_source(mirror, dir);
+            }
+            (key, (line, tfile))
+        })
+        .collect()
+}
+
+/// Directory of the file at `path`, empty for a bare filename
+fn parent(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new(""))
+}
+
+/// Adds filenames used by more than one asset, extract directories holding assets and
+/// overlapping extract directories to `problems`, and returns the assets if there are none
+fn check_duplicates(
+    assets: LocatedAssets,
+    mut problems: Vec<ManifestProblem>,
+) -> Result<TestAsset, Vec<ManifestProblem>> {
+    // `a.bin`, `./a.bin` and `.//a.bin` are the same file
+    let mut filenames: BTreeMap<PathBuf, &str> = BTreeMap::new();
+    let mut extract_dirs: Vec<(&str, &str)> = Vec::new();
+    for (key, (line, tfile)) in &assets {
+        let mut problem = |message: String| {
+            problems.push(ManifestProblem { key: Some(key.clone()), line: *line, message });
+        };
+        if let Some(other) = filenames.insert(normalize(&tfile.filename), key) {
+            problem(format!("filename `{}` is also used by {other}", tfile.filename));
+        }
+        let Some(ref subdir) = tfile.extract else {
+            continue;
+        };
+        for (other, dir) in &extract_dirs {
+            if is_within(dir, subdir) || is_within(subdir, dir) {
+                problem(format!(
+                    "extract directory `{subdir}` overlaps with `{dir}`, where {other} is extracted"
+                ));
+            }
+        }
+        extract_dirs.push((key, subdir));
+        for (other, (_, other_tfile)) in &assets {
+            if is_within(&other_tfile.filename, subdir) {
+                problem(format!(
+                    "extract directory `{subdir}` holds the filename `{}` of {other}",
+                    other_tfile.filename
+                ));
+            }
+        }
+    }
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    Ok(TestAsset { assets: assets.into_iter().map(|(key, (_, tfile))| (key, tfile)).collect() })
+}
+
+impl TestAsset {
+    /// Reads and validates the manifest at `path`
+    ///
+    /// A `Cargo.toml` is read like with [`TestAsset::from_cargo`].
+    ///
+    /// ```rust, no_run
+    /// # use test_assets_ureq::{dl_test_files, TestAsset};
+    /// let parsed = TestAsset::from_path("test.toml").unwrap();
+    /// dl_test_files(&parsed.values(), "test-assets", true).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    /// [`TaError::InvalidManifest`] with every problem found, see [`TestAsset::from_str`]
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, TaError> {
+        let path = path.as_ref();
+        if path.file_name().is_some_and(|name| name == "Cargo.toml") {
+            return Self::from_cargo_manifest(path);
+        }
+        let content = read(path)?;
+        parse_toml::<SpannedManifest>(&content)
+            .and_then(|manifest| {
+                let mut problems = Vec::new();
+                let assets = validate(&content, manifest.test_assets, parent(path), &mut problems);
+                check_duplicates(assets, problems)
+            })
+            .map_err(|problems| invalid(Some(path), problems))
+    }
+
+    /// Reads the assets of the crate being built or tested from its `Cargo.toml`
+    ///
+    /// The assets are the tables under `[package.metadata.test-assets]`, merged with those
+    /// under `[workspace.metadata.test-assets]` of the workspace root. Assets of the package
+    /// replace workspace assets with the same key.
+    /// ```toml
+    /// [package.metadata.test-assets.test_00]
+    /// filename = "out.squashfs"
+    /// hash = "976c1638d8c1ba8014de6c64b196cbd70a5acf031be10a8e7f649536193c8e78"
+    /// url = "https://wcampbell.dev/squashfs/testing/test_00/out.squashfs"
+    /// ```
+    ///
+    /// The `Cargo.toml` is found through `CARGO_MANIFEST_DIR`, which cargo sets for builds,
+    /// tests and `cargo run`, and otherwise is the nearest one in the current directory
+    /// or its parents.
+    ///
+    /// # Errors
+    /// If no `Cargo.toml` is found, or like [`TestAsset::from_path`]
+    pub fn from_cargo() -> Result<Self, TaError> {
+        let path = match env::var_os("CARGO_MANIFEST_DIR") {
+            Some(dir) => Path::new(&dir).join("Cargo.toml"),
+            None => env::current_dir()
+                .ok()
+                .and_then(|dir| {
+                    dir.ancestors().map(|d| d.join("Cargo.toml")).find(|path| path.is_file())
+                })
+                .ok_or_else(|| {
+                    let message = "no Cargo.toml in the current directory or its parents";
+                    let problem =
+                        ManifestProblem { key: None, line: None, message: message.into() };
+                    invalid(None, vec![problem])
+                })?,
+        };
+        Self::from_cargo_manifest(&path)
+    }
+
+    fn from_cargo_manifest(path: &Path) -> Result<Self, TaError> {
+        let content = read(path)?;
+        let manifest: CargoManifest =
+            parse_toml(&content).map_err(|problems| invalid(Some(path), problems))?;
+
+        let workspace = match manifest.workspace {
+            Some(workspace) => Some((path.to_owned(), content.clone(), workspace)),
+            None => find_workspace_root(path, manifest.package.as_ref())?,
+        };
+        let mut assets = LocatedAssets::new();
+        let mut problems = Vec::new();
+        if let Some((root, root_content, workspace)) = workspace {
+            let mut root_problems = Vec::new();
+            let workspace_assets = validate(
+                &root_content,
+                workspace.metadata.test_assets,
+                parent(&root),
+                &mut root_problems,
+            );
+            // Lines in another file would be misleading next to `path` in errors
+            let same_file = root == path;
+            if !same_file && !root_problems.is_empty() {
+                return Err(invalid(Some(&root), root_problems));
+            }
+            problems.extend(root_problems);
+            assets.extend(
+                workspace_assets
+                    .into_iter()
+                    .map(|(key, (line, tfile))| (key, (line.filter(|_| same_file), tfile))),
+            );
+        }
+        if let Some(package) = manifest.package {
+            let package_assets =
+                validate(&content, package.metadata.test_assets, parent(path), &mut problems);
+            assets.extend(package_assets);
+        }
+        check_duplicates(assets, problems).map_err(|problems| invalid(Some(path), problems))
+    }
+}
+
+/// Path, content and `[workspace]` of the workspace root containing the package at `path`
+///
+/// Like cargo, the closest parent with a `[workspace]` is the root, which only has the
+/// package if its `members` and `exclude` say so. A root named by `package.workspace`
+/// is trusted.
+fn find_workspace_root(
+    path: &Path,
+    package: Option<&CargoPackage>,
+) -> Result<Option<(PathBuf, String, CargoWorkspace)>, TaError> {
+    let dir =
+        absolute(path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new(".")));
+    let explicit = package.and_then(|p| p.workspace.as_deref());
+    let candidates: Vec<PathBuf> = match explicit {
+        Some(root) => vec![dir.join(root).join("Cargo.toml")],
+        None => dir.ancestors().skip(1).map(|d| d.join("Cargo.toml")).collect(),
+    };
+    for candidate in candidates {
+        if !candidate.is_file() {
+            continue;
+        }
+        let content = read(&candidate)?;
+        let manifest: CargoManifest =
+            parse_toml(&content).map_err(|problems| invalid(Some(&candidate), problems))?;
+        let Some(workspace) = manifest.workspace else {
+            continue;
+        };
+        let root = candidate.parent().unwrap_or(Path::new("/"));
+        if explicit.is_none() && !workspace.has_member(root, &dir) {
+            return Ok(None);
+        }
+        return Ok(Some((candidate, content, workspace)));
+    }
+    Ok(None)
+}
+
+impl FromStr for TestAsset {
+    type Err = TaError;
+
+    /// Parses and validates a manifest
+    ///
+    /// Besides TOML errors and unknown keys, malformed hashes, filenames used by more than one asset,
+    /// empty urls, filenames or extraction directories that aren't plain relative paths,
+    /// overlapping extraction directories and `hash_decompressed` without `decompress`
+    /// are reported, all at once.
+    fn from_str(s: &str) -> Result<Self, TaError> {
+        parse_toml::<SpannedManifest>(s)
+            .and_then(|manifest| {
+                let mut problems = Vec::new();
+                let assets = validate(s, manifest.test_assets, Path::new(""), &mut problems);
+                check_duplicates(assets, problems)
+            })
+            .map_err(|problems| invalid(None, problems))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA256: &str = "976c1638d8c1ba8014de6c64b196cbd70a5acf031be10a8e7f649536193c8e78";
+
+    fn problems(s: &str) -> Vec<ManifestProblem> {
+        match s.parse::<TestAsset>() {
+            Err(TaError::InvalidManifest { path: None, problems }) => problems,
+            result => panic!("{result:?}"),
+        }
+    }
+
+    fn has(problems: &[ManifestProblem], key: &str, line: usize, message: &str) -> bool {
+        problems.iter().any(|p| {
+            p.key.as_deref() == Some(key) && p.line == Some(line) && p.message.contains(message)
+        })
+    }
+
+    #[test]
+    fn reports_every_problem_with_its_line() {
+        let manifest = format!(
+            r#"[test_assets.a]
+filename = "a.bin"
+hash = "nothex"
+url = "https://example.com/a.bin"
+
+[test_assets.b]
+filename = "a.bin"
+hash = "{SHA256}"
+url = ""
+
+[test_assets.c]
+filename = "hash_list"
+hash = "{SHA256}"
+url = "https://example.com/c.bin"
+"#
+        );
+        let problems = problems(&manifest);
+        assert!(has(&problems, "a", 1, "not a valid hash"), "{problems:?}");
+        assert!(has(&problems, "b", 6, "empty url"), "{problems:?}");
+        // Duplicates are reported along with the problems of single assets
+        assert!(has(&problems, "b", 6, "also used by a"), "{problems:?}");
+        assert!(has(&problems, "c", 11, "hash list"), "{problems:?}");
+        assert_eq!(problems.len(), 4, "{problems:?}");
+    }
+
+    #[test]
+    fn rejects_extract_directories_holding_assets() {
+        let manifest = format!(
+            r#"[test_assets.fixtures]
+filename = "fixtures/data.tar"
+hash = "{SHA256}"
+url = "https://example.com/data.tar"
+extract = "fixtures"
+
+[test_assets.all]
+filename = "all.tar"
+hash = "{SHA256}"
+url = "https://example.com/all.tar"
+extract = "."
+"#
+        );
+        let problems = problems(&manifest);
+        assert!(has(&problems, "fixtures", 1, "holds the filename"), "{problems:?}");
+        assert!(has(&problems, "all", 7, "invalid extract directory `.`"), "{problems:?}");
+    }
+
+    #[test]
+    fn compares_normalized_paths() {
+        let manifest = format!(
+            r#"[test_assets.a]
+filename = "fw//a.bin"
+hash = "{SHA256}"
+url = "https://example.com/a.bin"
+
+[test_assets.b]
+filename = "./fw/a.bin"
+hash = "{SHA256}"
+url = "https://example.com/b.bin"
+
+[test_assets.c]
+filename = "c.tar"
+hash = "{SHA256}"
+url = "https://example.com/c.tar"
+extract = "data"
+
+[test_assets.d]
+filename = "d.tar"
+hash = "{SHA256}"
+url = "https://example.com/d.tar"
+extract = "./data/d"
+"#
+        );
+        let problems = problems(&manifest);
+        assert!(has(&problems, "b", 6, "also used by a"), "{problems:?}");
+        assert!(has(&problems, "d", 17, "where c is extracted"), "{problems:?}");
+        assert_eq!(problems.len(), 2, "{problems:?}");
+    }
+
+    #[test]
+    fn requires_decompress_for_hash_decompressed() {
+        let manifest = format!(
+            "[test_assets.a]\nfilename = \"a.bin\"\nhash = \"{SHA256}\"\n\
+             url = \"https://example.com/a.bin\"\nhash_decompressed = true\n"
+        );
+        let problems = problems(&manifest);
+        assert!(has(&problems, "a", 1, "`decompress`"), "{problems:?}");
+    }
+
+    #[test]
+    fn reports_toml_errors_with_their_line() {
+        let problems = problems("[test_assets.a]\nfilename = \"a.bin\"\nhash = \n");
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].line, Some(3));
+    }
+
+    #[test]
+    fn reports_unknown_keys_with_their_line() {
+        for typo in ["decompres = \"gzip\"", "hash_decompresed = true"] {
+            let manifest = format!(
+                "[test_assets.a]\nfilename = \"a.bin\"\nhash = \"{SHA256}\"\n\
+                 url = \"https://example.com/a.bin\"\n{typo}\n"
+            );
+            let problems = problems(&manifest);
+            let name = typo.split(' ').next().unwrap();
+            assert_eq!(problems.len(), 1, "{problems:?}");
+            assert_eq!(problems[0].line, Some(5), "{problems:?}");
+            assert!(problems[0].message.contains(&format!("`{name}`")), "{problems:?}");
+        }
+        let cargo = format!(
+            "[package]\nname = \"a\"\n\n[package.metadata.test-assets.a]\n\
+             filename = \"a.tar\"\nhash = \"{SHA256}\"\nurl = \"a.tar\"\nextarct = \"a\"\n"
+        );
+        let problems = parse_toml::<CargoManifest>(&cargo).err().unwrap();
+        assert_eq!(problems[0].line, Some(8), "{problems:?}");
+        assert!(problems[0].message.contains("`extarct`"), "{problems:?}");
+    }
+
+    #[test]
+    fn merges_assets_of_workspace_members_only() {
+        let root = crate::tests::temp_dir("cargo-workspace");
+        let asset = |key: &str| {
+            format!("[{key}]\nfilename = \"{key}.bin\"\nhash = \"{SHA256}\"\nurl = \"a.bin\"\n")
+        };
+        let workspace = format!(
+            "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/skipped\"]\n\n{}",
+            asset("workspace.metadata.test-assets.shared")
+        );
+        fs::write(Path::new(&root).join("Cargo.toml"), workspace).unwrap();
+        for name in ["member", "skipped"] {
+            let dir = Path::new(&root).join("crates").join(name);
+            fs::create_dir_all(&dir).unwrap();
+            let package = format!(
+                "[package]\nname = \"{name}\"\n\n{}",
+                asset(&format!("package.metadata.test-assets.{name}"))
+            );
+            fs::write(dir.join("Cargo.toml"), package).unwrap();
+        }
+
+        let member = TestAsset::from_path(format!("{root}/crates/member/Cargo.toml")).unwrap();
+        assert_eq!(member.assets.keys().collect::<Vec<_>>(), ["member", "shared"]);
+        let skipped = TestAsset::from_path(format!("{root}/crates/skipped/Cargo.toml")).unwrap();
+        assert_eq!(skipped.assets.keys().collect::<Vec<_>>(), ["skipped"]);
+    }
+
+    #[test]
+    fn matches_member_globs() {
+        assert!(matches_glob("crates/*", Path::new("crates/a")));
+        assert!(matches_glob("crates/a?c", Path::new("crates/abc")));
+        assert!(matches_glob("tool", Path::new("tool")));
+        assert!(!matches_glob("crates/*", Path::new("crates/a/b")));
+        assert!(!matches_glob("crates/*", Path::new("other/a")));
+    }
+
+    #[test]
+    fn resolves_sources_from_the_manifest_directory() {
+        let dir = crate::tests::temp_dir("manifest-sources");
+        let content = b"local source";
+        fs::write(Path::new(&dir).join("src.bin"), content).unwrap();
+        let hash = crate::tests::sha256(content);
+        let manifest = format!(
+            "[test_assets.a]\nfilename = \"a.bin\"\nhash = \"{hash}\"\n\
+             url = [\"src.bin\", \"https://example.com/a.bin\"]\n"
+        );
+        fs::write(Path::new(&dir).join("assets.toml"), &manifest).unwrap();
+
+        let parsed = TestAsset::from_path(format!("{dir}/assets.toml")).unwrap();
+        assert_eq!(parsed.assets["a"].url, format!("{dir}/src.bin"));
+        assert_eq!(parsed.assets["a"].mirrors, ["https://example.com/a.bin"]);
+        let downloaded =
+            crate::Downloader::new(&format!("{dir}/assets")).download_assets(&parsed).unwrap();
+        assert_eq!(fs::read(downloaded.path("a").unwrap()).unwrap(), content);
+        // Without a file, they stay relative to the current directory
+        let parsed: TestAsset = manifest.parse().unwrap();
+        assert_eq!(parsed.assets["a"].url, "src.bin");
+    }
+
+    #[test]
+    fn reports_urls_missing_their_scheme() {
+        let asset = |url: &str| {
+            format!("[test_assets.a]\nfilename = \"a.bin\"\nhash = \"{SHA256}\"\nurl = \"{url}\"\n")
+        };
+        let problems = problems(&asset("wcampbell.dev/squashfs/out.squashfs"));
+        assert!(has(&problems, "a", 1, "`https://wcampbell.dev/"), "{problems:?}");
+        for url in ["fixtures/a.bin", "./example.com/a.bin", "a.bin", "file:///tmp/a.bin"] {
+            assert!(asset(url).parse::<TestAsset>().is_ok(), "{url}");
+        }
+    }
+
+    #[test]
+    fn accepts_valid_manifests() {
+        let manifest = format!(
+            "[test_assets.a]\nfilename = \"fw/a.bin\"\nhash = \"{SHA256}\"\n\
+             url = [\"https://example.com/a.bin\", \"https://mirror.example.org/a.bin\"]\n"
+        );
+        let parsed: TestAsset = manifest.parse().unwrap();
+        assert_eq!(parsed.assets["a"].mirrors, ["https://mirror.example.org/a.bin"]);
+    }
+}
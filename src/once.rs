@@ -0,0 +1,76 @@
+/*!
+Process-wide memoization of downloads, for test functions running in parallel
+*/
+
+use crate::{DownloadedAssets, Downloader, TaError, TestAsset};
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+type Outcome = Result<DownloadedAssets, Arc<TaError>>;
+
+/// Outcome of every download so far, by manifest path and assets directory
+type Outcomes = HashMap<(PathBuf, PathBuf), Arc<OnceLock<Outcome>>>;
+
+static OUTCOMES: OnceLock<Mutex<Outcomes>> = OnceLock::new();
+
+/// `path` joined to the current directory, without resolving symlinks
+///
+/// Canonicalizing would give a different key once the directory is created.
+fn key_path(path: &str) -> PathBuf {
+    env::current_dir().map(|dir| dir.join(path)).unwrap_or_else(|_| PathBuf::from(path))
+}
+
+/// Runs `downloader` on the assets of `manifest` unless that already happened in this process
+///
+/// Callers arriving while the download is running wait for it.
+pub(crate) fn download_once(downloader: &Downloader, manifest: &str) -> Outcome {
+    let key = (key_path(manifest), key_path(&downloader.dir));
+    let outcome = {
+        let mut outcomes = OUTCOMES.get_or_init(Mutex::default).lock().unwrap();
+        Arc::clone(outcomes.entry(key).or_default())
+    };
+    outcome
+        .get_or_init(|| {
+            TestAsset::from_path(manifest)
+                .and_then(|assets| downloader.download_assets(&assets))
+                .map_err(Arc::new)
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::temp_dir;
+    use std::fs;
+    use std::thread;
+
+    #[test]
+    fn shares_the_outcome_with_concurrent_callers() {
+        let dir = temp_dir("once");
+        // An invalid hash fails without touching the network
+        let manifest = format!("{dir}/test-assets.toml");
+        let content = r#"
+            [test_assets.a]
+            filename = "a.bin"
+            hash = "nothex"
+            url = "https://assets.example.com/a.bin"
+        "#;
+        fs::write(&manifest, content).unwrap();
+        let downloader = Downloader::new(&format!("{dir}/assets"));
+
+        let errors: Vec<Arc<TaError>> = thread::scope(|s| {
+            let callers: Vec<_> = (0..4)
+                .map(|_| s.spawn(|| download_once(&downloader, &manifest).unwrap_err()))
+                .collect();
+            callers.into_iter().map(|caller| caller.join().unwrap()).collect()
+        });
+        assert!(matches!(*errors[0], TaError::InvalidManifest { .. }), "{:?}", errors[0]);
+        assert!(errors.iter().all(|e| Arc::ptr_eq(e, &errors[0])));
+        // Later calls don't retry either
+        let again = download_once(&downloader, &manifest).unwrap_err();
+        assert!(Arc::ptr_eq(&again, &errors[0]));
+    }
+}
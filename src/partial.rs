@@ -0,0 +1,45 @@
+/*!
+Bookkeeping for partially downloaded files
+*/
+
+use crate::FetchResponse;
+use std::fs::{read_to_string, write};
+use std::io::{self, ErrorKind};
+
+/// Describes the content of a `.part` file, so that an interrupted download can be
+/// resumed with a `Range` request instead of starting over
+pub struct PartInfo {
+    /// The url the partial content was fetched from
+    pub url: String,
+    /// `ETag` (or `Last-Modified` as fallback) of the partial content, sent as `If-Range`
+    pub validator: String,
+}
+
+impl PartInfo {
+    /// Extracts the info from a response, if the server provided a validator
+    pub fn from_response(url: &str, resp: &FetchResponse) -> Option<Self> {
+        let validator = resp.header("ETag").or_else(|| resp.header("Last-Modified"))?;
+        Some(Self { url: url.to_owned(), validator: validator.to_owned() })
+    }
+
+    pub fn from_file(path: &str) -> io::Result<Self> {
+        let content = read_to_string(path)?;
+        let mut lines = content.lines();
+        match (lines.next(), lines.next()) {
+            (Some(url), Some(validator)) => {
+                Ok(Self { url: url.to_owned(), validator: validator.to_owned() })
+            }
+            _ => Err(io::Error::new(ErrorKind::InvalidData, "malformed part info")),
+        }
+    }
+
+    pub fn to_file(&self, path: &str) -> io::Result<()> {
+        write(path, format!("{}\n{}\n", self.url, self.validator))
+    }
+}
+
+/// Returns the first byte position of a `206 Partial Content` response
+pub fn content_range_start(resp: &FetchResponse) -> Option<u64> {
+    let range = resp.header("Content-Range")?.strip_prefix("bytes ")?;
+    range.split('-').next()?.trim().parse().ok()
+}
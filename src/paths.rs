@@ -0,0 +1,148 @@
+/*!
+Placement of assets inside the assets directory
+*/
+
+use crate::TaError;
+use std::env;
+use std::fs::{canonicalize, create_dir_all, symlink_metadata};
+use std::path::{Component, Path, PathBuf};
+
+/// Resolves `filename` below `dir`, creating missing parent directories
+///
+/// Nested relative names like `fw/v1/image.bin` are fine, but names that could end
+/// up outside of `dir` are rejected: absolute paths, `..` components and symlinks.
+pub fn asset_path(dir: &str, filename: &str) -> Result<String, TaError> {
+    let invalid =
+        |reason: &'static str| TaError::InvalidFilename { filename: filename.to_owned(), reason };
+
+    check_relative(filename).map_err(invalid)?;
+
+    let path = format!("{dir}/{filename}");
+    if let Some(parent) = Path::new(&path).parent() {
+        create_dir_all(dir)?;
+        let root = canonicalize(dir)?;
+        let escapes = |p: &Path| Ok::<_, TaError>(!canonicalize(p)?.starts_with(&root));
+        // Nothing may be created outside of `dir`, so the part that already exists is checked first
+        let existing = parent.ancestors().find(|p| p.exists()).unwrap_or(parent);
+        if escapes(existing)? {
+            return Err(invalid("a symlink leads out of the assets directory"));
+        }
+        create_dir_all(parent)?;
+        if escapes(parent)? {
+            return Err(invalid("a symlink leads out of the assets directory"));
+        }
+    }
+    if symlink_metadata(&path).map(|m| m.file_type().is_symlink()).unwrap_or(false) {
+        return Err(invalid("it is a symlink"));
+    }
+    Ok(path)
+}
+
+/// Suffixes of the files the downloader keeps next to assets
+const RESERVED_SUFFIXES: &[&str] =
+    &[".lock", ".part", ".part.info", ".decompressing", ".cached", ".extracting"];
+
+/// Checks that `name` is a non-empty relative path that stays below the directory it is
+/// joined to, and doesn't clash with the files the downloader keeps there
+pub(crate) fn check_relative(name: &str) -> Result<(), &'static str> {
+    if name.is_empty() {
+        return Err("it is empty");
+    }
+    let mut normal = Vec::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(c) => normal.push(c),
+            Component::CurDir => {}
+            Component::ParentDir => return Err("it contains `..`"),
+            Component::RootDir | Component::Prefix(_) => return Err("it is absolute"),
+        }
+    }
+    let Some(last) = normal.last().map(|c| c.to_string_lossy()) else {
+        return Err("it is the assets directory itself");
+    };
+    if normal.len() == 1 && (last == "hash_list" || last.starts_with("hash_list.")) {
+        return Err("the hash list is stored under that name");
+    }
+    if RESERVED_SUFFIXES.iter().any(|suffix| last.ends_with(suffix)) {
+        return Err("that suffix is reserved for temporary and lock files");
+    }
+    Ok(())
+}
+
+/// The relative path `path` without `.` components and repeated separators
+pub(crate) fn normalize(path: &str) -> PathBuf {
+    Path::new(path).components().filter(|c| matches!(c, Component::Normal(_))).collect()
+}
+
+/// Whether the relative path `path` names `dir` or something below it
+pub(crate) fn is_within(path: &str, dir: &str) -> bool {
+    normalize(path).starts_with(normalize(dir))
+}
+
+/// `path` as absolute path, resolving symlinks if it exists
+pub(crate) fn absolute(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    canonicalize(path)
+        .or_else(|_| env::current_dir().map(|dir| dir.join(path)))
+        .unwrap_or_else(|_| path.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::temp_dir;
+
+    #[test]
+    fn checks_relative_names() {
+        for name in ["a.bin", "fw/v1/image.bin", "./a.bin", "hash_lists/a.bin", "data.tar"] {
+            assert_eq!(check_relative(name), Ok(()), "{name}");
+        }
+        for name in
+            ["", ".", "./", "../a.bin", "fw/../../a.bin", "/etc/passwd", "hash_list", "a.lock"]
+        {
+            assert!(check_relative(name).is_err(), "{name}");
+        }
+        assert!(check_relative("fw/a.bin.part").is_err());
+    }
+
+    #[test]
+    fn rejects_names_outside_of_the_directory() {
+        let dir = temp_dir("names");
+        for name in ["a.bin", "fw/v1/image.bin", "./a.bin"] {
+            assert!(asset_path(&dir, name).is_ok(), "{name}");
+        }
+        for name in ["", "../a.bin", "fw/../../a.bin", "/etc/passwd"] {
+            let err = asset_path(&dir, name).unwrap_err();
+            assert!(matches!(err, TaError::InvalidFilename { .. }), "{name}: {err:?}");
+        }
+    }
+
+    #[test]
+    fn compares_normal_components() {
+        assert!(is_within("data/a.bin", "data"));
+        assert!(is_within("./data/a.bin", "data/"));
+        assert!(is_within("data", "data"));
+        assert!(!is_within("database.bin", "data"));
+        assert!(!is_within("a.bin", "data"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlinks_out_of_the_directory() {
+        let outside = temp_dir("symlink-outside");
+        let dir = temp_dir("symlink-assets");
+        std::os::unix::fs::symlink(&outside, format!("{dir}/link")).unwrap();
+
+        let err = asset_path(&dir, "link/sub/a.bin").unwrap_err();
+        assert!(matches!(err, TaError::InvalidFilename { .. }), "{err:?}");
+        // Nothing may be created on the other side of the link
+        assert!(!Path::new(&outside).join("sub").exists());
+
+        std::os::unix::fs::symlink(format!("{outside}/a.bin"), format!("{dir}/a.bin")).unwrap();
+        let err = asset_path(&dir, "a.bin").unwrap_err();
+        assert!(matches!(err, TaError::InvalidFilename { .. }), "{err:?}");
+
+        assert_eq!(asset_path(&dir, "fw/a.bin").unwrap(), format!("{dir}/fw/a.bin"));
+        assert!(Path::new(&dir).join("fw").is_dir());
+    }
+}
@@ -0,0 +1,235 @@
+/*!
+Retrying of transient download failures
+*/
+
+use crate::FetchResponse;
+use crate::TaError;
+use backon::{BackoffBuilder, ExponentialBuilder};
+use std::io;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+/// Exponential backoff policy for retrying failed downloads
+///
+/// Only transient failures are retried, see [`TaError::is_retryable`].
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, before giving up
+    pub max_retries: usize,
+    /// Delay before the first retry, doubled for every following one
+    pub min_delay: Duration,
+    /// Upper bound for the exponential delay
+    pub max_delay: Duration,
+    /// Longest `Retry-After` of a server that is waited for, instead of giving up
+    pub max_retry_after: Duration,
+    /// Add a random delay to spread out retries of concurrent clients
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            min_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_retry_after: Duration::from_secs(120),
+            jitter: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Runs `f` until it succeeds, fails permanently or the retries are exhausted
+    ///
+    /// A `Retry-After` sent by the server takes precedence over a shorter delay, and one
+    /// longer than `max_retry_after` fails right away instead of waiting for it.
+    pub fn retry<T>(&self, mut f: impl FnMut() -> Result<T, TaError>) -> Result<T, TaError> {
+        let mut builder = ExponentialBuilder::default()
+            .with_min_delay(self.min_delay)
+            .with_max_delay(self.max_delay)
+            .with_max_times(self.max_retries);
+        if self.jitter {
+            builder = builder.with_jitter();
+        }
+        let mut delays = builder.build();
+        loop {
+            let err = match f() {
+                Ok(v) => return Ok(v),
+                Err(e) if e.is_retryable() => e,
+                Err(e) => return Err(e),
+            };
+            let retry_after = err.retry_after().unwrap_or_default();
+            match delays.next() {
+                Some(delay) if retry_after <= self.max_retry_after => sleep(delay.max(retry_after)),
+                _ => return Err(err),
+            }
+        }
+    }
+}
+
+impl TaError {
+    /// Whether trying again later could succeed
+    ///
+    /// Network hiccups, timeouts, `429 Too Many Requests` and server errors are
+    /// transient. Hash mismatches, invalid hashes or urls and local failures are not.
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport { source, .. } => source.transient,
+            Self::Status { status, .. } => matches!(status, 408 | 429 | 500..=599),
+            Self::AssetIo { source, .. } => matches!(
+                source.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::TimedOut
+                    | io::ErrorKind::UnexpectedEof
+                    | io::ErrorKind::Interrupted
+            ),
+            Self::BadContentRange { .. } | Self::TruncatedBody { .. } => true,
+            Self::AllMirrorsFailed { errors, .. } | Self::Multiple(errors) => {
+                errors.iter().any(Self::is_retryable)
+            }
+            Self::Io(_)
+            | Self::HashMismatch { .. }
+            | Self::BadHashFormat { .. }
+            | Self::InvalidFilename { .. }
+            | Self::UnsupportedArchive { .. }
+            | Self::ExtractConflict { .. }
+            | Self::ExtractOverlap { .. }
+            | Self::Offline { .. }
+            | Self::InsecureRedirect { .. }
+            | Self::MissingCredentials { .. }
+            | Self::InvalidManifest { .. } => false,
+        }
+    }
+
+    /// Delay requested by the server through `Retry-After`
+    #[must_use]
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Status { retry_after, .. } => *retry_after,
+            Self::AllMirrorsFailed { errors, .. } | Self::Multiple(errors) => {
+                errors.iter().filter_map(Self::retry_after).max()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Parses `Retry-After`, given either in seconds or as an HTTP date
+pub(crate) fn parse_retry_after(resp: &FetchResponse) -> Option<Duration> {
+    let value = resp.header("Retry-After")?.trim();
+    if let Ok(secs) = value.parse() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn status(status: u16) -> TaError {
+        TaError::Status {
+            filename: "a.bin".to_owned(),
+            url: "https://example.com/a.bin".to_owned(),
+            status,
+            retry_after: None,
+        }
+    }
+
+    fn retry_after(delay: Duration) -> TaError {
+        TaError::Status {
+            filename: "a.bin".to_owned(),
+            url: "https://example.com/a.bin".to_owned(),
+            status: 503,
+            retry_after: Some(delay),
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            min_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn classifies_errors() {
+        for code in [408, 429, 500, 503] {
+            assert!(status(code).is_retryable(), "{code}");
+        }
+        for code in [401, 403, 404] {
+            assert!(!status(code).is_retryable(), "{code}");
+        }
+        let mismatch = TaError::HashMismatch {
+            filename: "a.bin".to_owned(),
+            expected: "a".to_owned(),
+            found: "b".to_owned(),
+        };
+        assert!(!mismatch.is_retryable());
+        assert!(TaError::Multiple(vec![status(404), status(503)]).is_retryable());
+        assert!(!TaError::Multiple(vec![status(404), status(403)]).is_retryable());
+    }
+
+    #[test]
+    fn retries_transient_errors_only() {
+        let mut attempts = 0;
+        let result: Result<(), _> = policy().retry(|| {
+            attempts += 1;
+            Err(status(503))
+        });
+        assert!(matches!(result, Err(TaError::Status { status: 503, .. })));
+        assert_eq!(attempts, 4);
+
+        let mut attempts = 0;
+        let result: Result<(), _> = policy().retry(|| {
+            attempts += 1;
+            Err(status(404))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        let mut attempts = 0;
+        let result = policy().retry(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(status(500))
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn waits_for_retry_after() {
+        // Longer than `max_delay`, but within `max_retry_after`
+        let delay = Duration::from_millis(50);
+        let mut attempts = 0;
+        let start = Instant::now();
+        let result = policy().retry(|| {
+            attempts += 1;
+            if attempts < 2 {
+                Err(retry_after(delay))
+            } else {
+                Ok(())
+            }
+        });
+        assert!(result.is_ok());
+        assert!(start.elapsed() >= delay);
+
+        let policy = RetryPolicy { max_retry_after: delay, ..policy() };
+        let mut attempts = 0;
+        let result: Result<(), _> = policy.retry(|| {
+            attempts += 1;
+            Err(retry_after(Duration::from_secs(3600)))
+        });
+        assert_eq!(result.unwrap_err().retry_after(), Some(Duration::from_secs(3600)));
+        assert_eq!(attempts, 1);
+    }
+}